@@ -0,0 +1,48 @@
+use std::path::PathBuf;
+
+use thiserror::Error as ThisError;
+
+/// Errors that can occur while requesting, saving, or announcing a screenshot
+#[derive(ThisError, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Ashpd(#[from] ashpd::Error),
+    #[error("couldn't determine a directory to save the screenshot to (requested: {0:?})")]
+    MissingSaveDirectory(Option<PathBuf>),
+    #[error(
+        "invalid --filename-template {template:?}: contains an unsupported strftime specifier"
+    )]
+    InvalidFilenameTemplate { template: String },
+    #[error("failed to save screenshot ({context}): {error}")]
+    SaveScreenshot {
+        error: std::io::Error,
+        context: &'static str,
+    },
+    #[error("failed to convert screenshot to {format}: {error}")]
+    ConvertImage {
+        error: image::ImageError,
+        format: &'static str,
+    },
+    #[error("failed to send desktop notification: {0}")]
+    Notify(#[source] zbus::Error),
+    #[error("failed to copy screenshot to the clipboard: {0}")]
+    Clipboard(#[source] arboard::Error),
+}
+
+impl Error {
+    /// Whether this error represents the user cancelling the portal request rather than a
+    /// genuine failure
+    pub fn cancelled(&self) -> bool {
+        matches!(
+            self,
+            Error::Ashpd(ashpd::Error::Response(
+                ashpd::desktop::ResponseError::Cancelled
+            ))
+        )
+    }
+
+    /// Render this error as a short message suitable for showing in a notification body
+    pub fn to_user_facing(&self) -> String {
+        self.to_string()
+    }
+}
@@ -2,9 +2,16 @@
 
 mod error;
 
-use ashpd::desktop::screenshot::Screenshot;
-use clap::{command, ArgAction, Parser};
-use std::{collections::HashMap, fs, os::unix::fs::MetadataExt, path::PathBuf};
+use ashpd::desktop::{screenshot::Screenshot, Color};
+use clap::{command, ArgAction, Parser, ValueEnum};
+use futures_util::StreamExt;
+use image::ImageEncoder;
+use std::{
+    collections::HashMap,
+    fs,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+};
 use tracing::{debug, error, info};
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 use zbus::{dbus_proxy, zvariant::Value, Connection};
@@ -41,6 +48,113 @@ struct Args {
     /// The directory to save the screenshot to, if not performing an interactive screenshot
     #[clap(short, long)]
     save_dir: Option<PathBuf>,
+    /// Pick a color from the screen instead of taking a screenshot, printing the result to stdout
+    #[clap(long)]
+    pick_color: bool,
+    /// Format to print the picked color in when `--pick-color` is set
+    #[clap(long, value_enum, default_value("hex"))]
+    color_format: ColorFormat,
+    /// Image format to save the screenshot as when it is saved under `--save-dir` (with
+    /// `--interactive=false`); interactive captures are left as the portal's PNG
+    #[clap(long, value_enum, default_value("png"))]
+    format: ImageFormat,
+    /// Quality to use when re-encoding into jpeg or avif; ignored for `--format png` or
+    /// `--format webp` (webp re-encoding is always lossless)
+    #[clap(long, default_value_t = 80, value_parser = clap::value_parser!(u8).range(0..=100))]
+    quality: u8,
+    /// strftime-style template, expanded against the local time, used to build the screenshot's
+    /// filename relative to `--save-dir`; may contain `/` to nest into date-based subdirectories
+    #[clap(long)]
+    filename_template: Option<String>,
+    /// Also copy the captured image to the clipboard
+    #[clap(long)]
+    clipboard: bool,
+    /// Copy the captured image to the clipboard and skip saving a file entirely
+    #[clap(long)]
+    clipboard_only: bool,
+    /// Wait up to 30s after a notification is shown for the user to click an action button
+    /// (Open, Open Folder, Copy Path) before exiting; by default the CLI exits immediately after
+    /// sending the notification, which is what scripted/keybinding invocations expect
+    #[clap(long,
+        default_missing_value("true"),
+        default_value("false"),
+        num_args(0..=1),
+        require_equals(true),
+        action = ArgAction::Set)]
+    wait_for_notification_action: bool,
+    /// Play a shutter sound on successful capture
+    #[clap(long,
+        default_missing_value("true"),
+        default_value("false"),
+        num_args(0..=1),
+        require_equals(true),
+        action = ArgAction::Set)]
+    sound: bool,
+    /// Flash the screen on successful capture, if supported by the running compositor
+    #[clap(long,
+        default_missing_value("true"),
+        default_value("false"),
+        num_args(0..=1),
+        require_equals(true),
+        action = ArgAction::Set)]
+    flash: bool,
+}
+
+/// Default `--filename-template` used when the flag is absent
+const DEFAULT_FILENAME_TEMPLATE: &str = "Screenshot_%Y-%m-%d_%H-%M-%S";
+
+/// Expand a `--filename-template` against `date`, catching unsupported strftime specifiers
+/// instead of letting chrono's `Display` impl panic through `format!`
+fn expand_filename_template(
+    template: &str,
+    date: chrono::DateTime<chrono::Local>,
+) -> Result<String, Error> {
+    use std::fmt::Write;
+
+    let mut expanded = String::new();
+    write!(expanded, "{}", date.format(template)).map_err(|_| Error::InvalidFilenameTemplate {
+        template: template.to_string(),
+    })?;
+    Ok(expanded)
+}
+
+/// Image format to save the screenshot as
+#[derive(ValueEnum, Default, Debug, Clone, Copy, PartialEq, Eq)]
+enum ImageFormat {
+    #[default]
+    Png,
+    Webp,
+    Jpeg,
+    Avif,
+}
+
+impl ImageFormat {
+    /// File extension (without the leading dot) used for this format
+    fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Webp => "webp",
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Avif => "avif",
+        }
+    }
+}
+
+/// Output format for `--pick-color`
+#[derive(ValueEnum, Default, Debug, Clone, Copy, PartialEq, Eq)]
+enum ColorFormat {
+    /// `#RRGGBB`
+    #[default]
+    Hex,
+    /// `rgb(r, g, b)` using 8-bit components
+    Rgb,
+    /// Raw normalized `red, green, blue` floats as returned by the portal
+    Float,
+}
+
+/// Convert a normalized color component in `0.0..=1.0` to an 8-bit channel value
+fn channel_to_u8(component: f64) -> u8 {
+    (component.clamp(0.0, 1.0) * 255.0).round() as u8
 }
 
 #[dbus_proxy(assume_defaults = true)]
@@ -57,34 +171,316 @@ trait Notifications {
         hints: HashMap<&str, &Value<'_>>,
         expire_timeout: i32,
     ) -> zbus::Result<u32>;
+
+    /// Call the org.freedesktop.Notifications.GetCapabilities D-Bus method
+    fn get_capabilities(&self) -> zbus::Result<Vec<String>>;
+
+    /// The org.freedesktop.Notifications.ActionInvoked signal, emitted when the user clicks one
+    /// of the buttons passed in `actions`
+    #[dbus_proxy(signal)]
+    fn action_invoked(&self, id: u32, action_key: &str) -> zbus::Result<()>;
 }
 
-// Send a notification for the screenshot app
-async fn send_notify(summary: &str, body: &str) -> Result<(), Error> {
+/// How long to keep the process alive waiting for the user to click a notification button
+const ACTION_WAIT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+// Send a notification for the screenshot app, offering Open/Open Folder/Copy Path buttons when
+// `path` points at a saved screenshot and the notification server supports actions. Only waits
+// around for a button click when `wait_for_action` is set; otherwise returns as soon as the
+// notification is posted, which is what scripted/keybinding invocations expect
+async fn send_notify(
+    summary: &str,
+    body: &str,
+    path: Option<&Path>,
+    wait_for_action: bool,
+) -> Result<(), Error> {
     let connection = Connection::session().await.map_err(Error::Notify)?;
 
     let proxy = NotificationsProxy::new(&connection)
         .await
         .map_err(Error::Notify)?;
-    proxy
+
+    let supports_actions = proxy
+        .get_capabilities()
+        .await
+        .map(|capabilities| {
+            capabilities
+                .iter()
+                .any(|capability| capability == "actions")
+        })
+        .unwrap_or(false);
+
+    let actions: &[&str] = if path.is_some() && supports_actions {
+        &[
+            "open",
+            "Open",
+            "open-folder",
+            "Open Folder",
+            "copy-path",
+            "Copy Path",
+        ]
+    } else {
+        &[]
+    };
+
+    let id = proxy
         .notify(
             "Cosmic Screenshot",
             0,
             "camera-photo-symbolic",
             summary,
             body,
-            &[],
+            actions,
             HashMap::new(),
             5000,
         )
         .await
-        .map_err(Error::Notify)
+        .map_err(Error::Notify)?;
+
+    if wait_for_action && supports_actions {
+        if let Some(path) = path {
+            await_notification_action(&proxy, id, path).await;
+        }
+    }
+
+    Ok(())
+}
+
+/// Wait briefly for an `ActionInvoked` signal matching `id` and perform the requested action
+async fn await_notification_action(proxy: &NotificationsProxy<'_>, id: u32, path: &Path) {
+    let Ok(mut signals) = proxy.receive_action_invoked().await else {
+        return;
+    };
+
+    let action = tokio::time::timeout(ACTION_WAIT_TIMEOUT, async {
+        while let Some(signal) = signals.next().await {
+            let Ok(args) = signal.args() else {
+                continue;
+            };
+            if *args.id() == id {
+                return Some(args.action_key().to_string());
+            }
+        }
+        None
+    })
+    .await
+    .ok()
+    .flatten();
+
+    match action.as_deref() {
+        Some("open") => open_path(path),
+        Some("open-folder") => {
+            if let Some(parent) = path.parent() {
+                open_path(parent);
+            }
+        }
+        Some("copy-path") => copy_path_to_clipboard(path),
+        _ => {}
+    }
+}
+
+/// Launch `path` with the user's default handler via `xdg-open`
+fn open_path(path: &Path) {
+    if let Err(error) = std::process::Command::new("xdg-open").arg(path).spawn() {
+        error!("Failed to launch xdg-open for {}: {error}", path.display());
+    }
+}
+
+/// Copy `path` as text onto the Wayland clipboard via `wl-copy`
+fn copy_path_to_clipboard(path: &Path) {
+    use std::io::Write;
+
+    let child = std::process::Command::new("wl-copy")
+        .stdin(std::process::Stdio::piped())
+        .spawn();
+    match child {
+        Ok(mut child) => {
+            if let Some(mut stdin) = child.stdin.take() {
+                if let Err(error) = stdin.write_all(path.to_string_lossy().as_bytes()) {
+                    error!("Failed to write path to wl-copy: {error}");
+                }
+            }
+        }
+        Err(error) => error!("Failed to launch wl-copy: {error}"),
+    }
+}
+
+/// The freedesktop shutter sound, as played by GNOME Shell's own screenshot UI
+const SHUTTER_SOUND_PATH: &str = "/usr/share/sounds/freedesktop/stereo/screen-capture.oga";
+
+/// Play the shutter sound via PipeWire; a no-op (with a logged error) if `pw-play` isn't installed
+fn play_shutter_sound() {
+    if let Err(error) = std::process::Command::new("pw-play")
+        .arg(SHUTTER_SOUND_PATH)
+        .spawn()
+    {
+        error!("Failed to play shutter sound via pw-play: {error}");
+    }
+}
+
+/// Ask the running compositor to flash the screen. No freedesktop portal exposes this, so it
+/// only works against compositors (such as COSMIC) that implement this interface directly; it's
+/// a silent no-op everywhere else
+async fn flash_screen() {
+    let Ok(connection) = Connection::session().await else {
+        return;
+    };
+
+    let result = connection
+        .call_method(
+            Some("com.system76.CosmicScreenshot"),
+            "/com/system76/CosmicScreenshot",
+            Some("com.system76.CosmicScreenshot"),
+            "Flash",
+            &(),
+        )
+        .await;
+
+    if let Err(error) = result {
+        debug!("Screen flash not supported by the running compositor: {error}");
+    }
+}
+
+#[tracing::instrument]
+async fn request_color(format: ColorFormat) -> Result<String, Error> {
+    let color = Color::pick().send().await?.response()?;
+    let (red, green, blue) = (color.red(), color.green(), color.blue());
+
+    Ok(match format {
+        ColorFormat::Hex => format!(
+            "#{:02X}{:02X}{:02X}",
+            channel_to_u8(red),
+            channel_to_u8(green),
+            channel_to_u8(blue)
+        ),
+        ColorFormat::Rgb => format!(
+            "rgb({}, {}, {})",
+            channel_to_u8(red),
+            channel_to_u8(green),
+            channel_to_u8(blue)
+        ),
+        ColorFormat::Float => format!("{red}, {green}, {blue}"),
+    })
+}
+
+/// Decode the PNG the portal wrote to `tmp_path` and re-encode it as `format` at `path`,
+/// applying `quality` for the formats that support it
+fn convert_screenshot(
+    tmp_path: &Path,
+    path: &Path,
+    format: ImageFormat,
+    quality: u8,
+) -> Result<(), Error> {
+    let map_convert_err = |error| Error::ConvertImage {
+        error,
+        format: format.extension(),
+    };
+
+    let image = image::open(tmp_path).map_err(map_convert_err)?;
+    let mut file = fs::File::create(path).map_err(|error| Error::SaveScreenshot {
+        error,
+        context: "creating re-encoded screenshot",
+    })?;
+
+    match format {
+        ImageFormat::Jpeg => image::codecs::jpeg::JpegEncoder::new_with_quality(&mut file, quality)
+            .encode_image(&image)
+            .map_err(map_convert_err),
+        ImageFormat::Avif => {
+            image::codecs::avif::AvifEncoder::new_with_speed_quality(&mut file, 4, quality)
+                .write_image(
+                    image.as_bytes(),
+                    image.width(),
+                    image.height(),
+                    image.color(),
+                )
+                .map_err(map_convert_err)
+        }
+        ImageFormat::Webp => image
+            .write_to(&mut file, image::ImageFormat::WebP)
+            .map_err(map_convert_err),
+        ImageFormat::Png => unreachable!("png is saved via the fast move/copy path"),
+    }
+}
+
+/// Env var that marks a re-exec of this binary as the detached background process spawned by
+/// [`spawn_clipboard_holder`], rather than a normal CLI invocation
+const CLIPBOARD_HOLD_PATH_ENV: &str = "COSMIC_SCREENSHOT_CLIPBOARD_PATH";
+/// Env var telling the clipboard-holder process whether to delete `CLIPBOARD_HOLD_PATH_ENV`
+/// once the clipboard selection is taken (`"1"` for `--clipboard-only`'s temp file, `"0"` for a
+/// screenshot that was also saved to disk)
+const CLIPBOARD_HOLD_CLEANUP_ENV: &str = "COSMIC_SCREENSHOT_CLIPBOARD_CLEANUP";
+
+/// Place the image at `path` on the clipboard, blocking until another application takes
+/// ownership of the selection (required for clipboard data to survive on Wayland). This blocks
+/// indefinitely, so it must only ever run in the detached process spawned by
+/// [`spawn_clipboard_holder`], never in the foreground CLI
+fn hold_image_on_clipboard(path: &Path, cleanup: bool) -> Result<(), Error> {
+    use arboard::SetExtLinux;
+
+    let image = image::open(path)
+        .map_err(|error| Error::ConvertImage {
+            error,
+            format: "clipboard",
+        })?
+        .into_rgba8();
+    let (width, height) = image.dimensions();
+    let image_data = arboard::ImageData {
+        width: width as usize,
+        height: height as usize,
+        bytes: std::borrow::Cow::Owned(image.into_raw()),
+    };
+
+    let result = arboard::Clipboard::new()
+        .map_err(Error::Clipboard)?
+        .set()
+        .wait()
+        .image(image_data)
+        .map_err(Error::Clipboard);
+
+    if cleanup && result.is_ok() {
+        let _ = fs::remove_file(path);
+    }
+
+    result
+}
+
+/// Re-exec the current binary as a detached background process that holds `path` on the
+/// clipboard via [`hold_image_on_clipboard`], so the foreground CLI can notify and exit
+/// immediately instead of blocking on `SetExtLinux::wait` itself
+fn spawn_clipboard_holder(path: &Path, cleanup: bool) -> Result<(), Error> {
+    let exe = std::env::current_exe().map_err(|error| Error::SaveScreenshot {
+        error,
+        context: "locating the current executable to hold the clipboard",
+    })?;
+
+    std::process::Command::new(exe)
+        .env(CLIPBOARD_HOLD_PATH_ENV, path)
+        .env(CLIPBOARD_HOLD_CLEANUP_ENV, if cleanup { "1" } else { "0" })
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .map_err(|error| Error::SaveScreenshot {
+            error,
+            context: "spawning the clipboard-holder process",
+        })
         .map(|_| ())
 }
 
+/// Outcome of a successful [`request_screenshot`] call
+enum CaptureOutcome {
+    /// The screenshot was saved to this path
+    Saved(String),
+    /// `--clipboard-only` was set, so the image was only copied to the clipboard and no file
+    /// was written
+    ClipboardOnly,
+}
+
 #[tracing::instrument]
-async fn request_screenshot(args: Args) -> Result<String, Error> {
-    let picture_dir = (!args.interactive)
+async fn request_screenshot(args: Args) -> Result<CaptureOutcome, Error> {
+    // `--clipboard-only` never writes a file, so it doesn't need a resolvable save directory
+    let picture_dir = (!args.interactive && !args.clipboard_only)
         .then(|| {
             args.save_dir
                 .clone()
@@ -105,43 +501,82 @@ async fn request_screenshot(args: Args) -> Result<String, Error> {
     debug!("Screenshot request URI: {uri}");
     match uri.scheme() {
         "file" => {
+            let tmp_path = Path::new(uri.path());
+
+            if args.clipboard_only {
+                spawn_clipboard_holder(tmp_path, true)?;
+                return Ok(CaptureOutcome::ClipboardOnly);
+            }
+
             if let Some(picture_dir) = picture_dir {
                 let date = chrono::Local::now();
-                let filename = format!("Screenshot_{}.png", date.format("%Y-%m-%d_%H-%M-%S"));
+                let template = args
+                    .filename_template
+                    .as_deref()
+                    .unwrap_or(DEFAULT_FILENAME_TEMPLATE);
+                let expanded = expand_filename_template(template, date)?;
+                let filename = format!("{expanded}.{}", args.format.extension());
                 let path = picture_dir.join(filename);
-                let tmp_path = uri.path();
-                if fs::metadata(&picture_dir)
-                    .map_err(|error| Error::SaveScreenshot {
-                        error,
-                        context: "metadata for screenshot destination",
-                    })?
-                    .dev()
-                    != fs::metadata(tmp_path)
+
+                let dest_dir = match path.parent() {
+                    Some(parent) if parent != picture_dir => {
+                        fs::create_dir_all(parent).map_err(|error| Error::SaveScreenshot {
+                            error,
+                            context: "creating screenshot directory",
+                        })?;
+                        parent
+                    }
+                    _ => picture_dir.as_path(),
+                };
+
+                if args.format == ImageFormat::Png {
+                    if fs::metadata(dest_dir)
                         .map_err(|error| Error::SaveScreenshot {
                             error,
-                            context: "metadata for temporary path",
+                            context: "metadata for screenshot destination",
                         })?
                         .dev()
-                {
-                    // copy file instead
-                    fs::copy(tmp_path, &path).map_err(|error| Error::SaveScreenshot {
-                        error,
-                        context: "copying screenshot",
-                    })?;
+                        != fs::metadata(tmp_path)
+                            .map_err(|error| Error::SaveScreenshot {
+                                error,
+                                context: "metadata for temporary path",
+                            })?
+                            .dev()
+                    {
+                        // copy file instead
+                        fs::copy(tmp_path, &path).map_err(|error| Error::SaveScreenshot {
+                            error,
+                            context: "copying screenshot",
+                        })?;
+                        fs::remove_file(tmp_path).map_err(|error| Error::SaveScreenshot {
+                            error,
+                            context: "removing temporary screenshot",
+                        })?;
+                    } else {
+                        fs::rename(tmp_path, &path).map_err(|error| Error::SaveScreenshot {
+                            error,
+                            context: "moving screenshot",
+                        })?;
+                    }
+                } else {
+                    convert_screenshot(tmp_path, &path, args.format, args.quality)?;
                     fs::remove_file(tmp_path).map_err(|error| Error::SaveScreenshot {
                         error,
                         context: "removing temporary screenshot",
                     })?;
-                } else {
-                    fs::rename(tmp_path, &path).map_err(|error| Error::SaveScreenshot {
-                        error,
-                        context: "moving screenshot",
-                    })?;
                 }
 
-                Ok(path.to_string_lossy().to_string())
+                if args.clipboard {
+                    spawn_clipboard_holder(&path, false)?;
+                }
+
+                Ok(CaptureOutcome::Saved(path.to_string_lossy().to_string()))
             } else {
-                Ok(uri.path().to_string())
+                if args.clipboard {
+                    spawn_clipboard_holder(tmp_path, false)?;
+                }
+
+                Ok(CaptureOutcome::Saved(uri.path().to_string()))
             }
         }
         scheme => {
@@ -153,6 +588,16 @@ async fn request_screenshot(args: Args) -> Result<String, Error> {
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() {
+    // If we're the detached process spawned by `spawn_clipboard_holder`, just hold the clipboard
+    // and exit; don't run any of the normal CLI logic below.
+    if let Ok(path) = std::env::var(CLIPBOARD_HOLD_PATH_ENV) {
+        let cleanup = std::env::var(CLIPBOARD_HOLD_CLEANUP_ENV).as_deref() == Ok("1");
+        if let Err(e) = hold_image_on_clipboard(Path::new(&path), cleanup) {
+            error!("Failed to hold clipboard selection: {e}");
+        }
+        return;
+    }
+
     // Init tracing but don't panic if it fails
     let _ = tracing_subscriber::registry()
         .with(fmt::layer())
@@ -161,25 +606,74 @@ async fn main() {
 
     let args = Args::parse();
     let notify = args.notify;
+    let wait_for_notification_action = args.wait_for_notification_action;
+    // Shutter sound/flash feedback only makes sense for an actual screen capture, not a color pick
+    let sound = args.sound && !args.pick_color;
+    let flash = args.flash && !args.pick_color;
 
-    let (summary, body) = match request_screenshot(args).await {
-        Ok(path) => {
-            info!("Screenshot saved to {path}");
-            ("Screenshot captured", path)
+    let (summary, body, saved_path, success) = if args.pick_color {
+        match request_color(args.color_format).await {
+            Ok(color) => {
+                println!("{color}");
+                info!("Picked color {color}");
+                ("Color picked", color, None, true)
+            }
+            Err(e) => {
+                if !e.cancelled() {
+                    error!("Color pick failed with {e}");
+                    ("Color pick failed", e.to_user_facing(), None, false)
+                } else {
+                    info!("Color pick cancelled");
+                    ("Color pick cancelled", "".into(), None, false)
+                }
+            }
         }
-        Err(e) => {
-            if !e.cancelled() {
-                error!("Screenshot failed with {e}");
-                ("Screenshot failed", e.to_user_facing())
-            } else {
-                info!("Screenshot cancelled");
-                ("Screenshot cancelled", "".into())
+    } else {
+        match request_screenshot(args).await {
+            Ok(CaptureOutcome::Saved(path)) => {
+                info!("Screenshot saved to {path}");
+                let saved_path = PathBuf::from(&path);
+                ("Screenshot captured", path, Some(saved_path), true)
+            }
+            Ok(CaptureOutcome::ClipboardOnly) => {
+                info!("Screenshot copied to the clipboard");
+                (
+                    "Screenshot captured",
+                    "Copied to the clipboard".to_string(),
+                    None,
+                    true,
+                )
+            }
+            Err(e) => {
+                if !e.cancelled() {
+                    error!("Screenshot failed with {e}");
+                    ("Screenshot failed", e.to_user_facing(), None, false)
+                } else {
+                    info!("Screenshot cancelled");
+                    ("Screenshot cancelled", "".into(), None, false)
+                }
             }
         }
     };
 
+    if success {
+        if sound {
+            play_shutter_sound();
+        }
+        if flash {
+            flash_screen().await;
+        }
+    }
+
     if notify {
-        if let Err(e) = send_notify(summary, &body).await {
+        if let Err(e) = send_notify(
+            summary,
+            &body,
+            saved_path.as_deref(),
+            wait_for_notification_action,
+        )
+        .await
+        {
             error!("Failed to post notification on completion: {e}");
         }
     }